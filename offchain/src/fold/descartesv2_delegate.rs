@@ -2,10 +2,14 @@ use offchain_core::ethers;
 
 use crate::contracts::descartesv2_contract::*;
 
+use super::epoch_cache::EpochFoldCache;
 use super::epoch_delegate::{ContractPhase, EpochFoldDelegate, EpochState};
+use super::finality::{compute_epoch_transitions, EpochTransition};
 use super::sealed_epoch_delegate::SealedEpochState;
+use super::state_store::StateStore;
 use super::types::{
-    AccumulatingEpoch, DescartesV2State, ImmutableState, PhaseState,
+    AccumulatingEpoch, DescartesV2State, FinalizedEpoch, ImmutableState,
+    PhaseState,
 };
 
 use offchain_core::types::Block;
@@ -17,27 +21,156 @@ use state_fold::{
 };
 
 use async_trait::async_trait;
-use snafu::ResultExt;
+use snafu::{ResultExt, Snafu};
 use std::sync::Arc;
 
 use ethers::providers::Middleware;
 use ethers::types::{Address, U256};
 
+/// A previously computed `DescartesV2State`, valid as of `block`. When
+/// provided to `DescartesV2FoldDelegate::new`, `sync` folds forward from
+/// this accumulator instead of replaying from contract creation.
+#[derive(Clone, Debug)]
+pub struct DescartesV2Checkpoint {
+    pub block: Block,
+    pub state: DescartesV2State,
+}
+
+/// Errors specific to the checkpoint mechanism. Passed through as the
+/// `source` of `state_fold`'s own error type at the call site (rather than
+/// stringified), so callers can `downcast_ref::<DescartesV2DelegateError>`
+/// the failure instead of matching on formatted text.
+#[derive(Debug, Snafu)]
+pub enum DescartesV2DelegateError {
+    #[snafu(display(
+        "state unavailable: requested block {} is older than checkpoint block {}",
+        requested_block,
+        checkpoint_block
+    ))]
+    StateUnavailable {
+        requested_block: u64,
+        checkpoint_block: u64,
+    },
+}
+
 /// DescartesV2 StateActor Delegate, which implements `sync` and `fold`.
 pub struct DescartesV2FoldDelegate<DA: DelegateAccess + Send + Sync + 'static> {
     descartesv2_address: Address,
     epoch_fold: Arc<StateFold<EpochFoldDelegate<DA>, DA>>,
+    epoch_cache: Option<EpochFoldCache>,
+    checkpoint: Option<DescartesV2Checkpoint>,
+    transitions_log: std::sync::Mutex<Vec<EpochTransition>>,
+    state_store: Option<Arc<dyn StateStore>>,
+    safety_margin: u64,
+    // States folded but not yet old enough, by this delegate's own count of
+    // confirmations, to trust with `state_store`. A block only leaves this
+    // queue (and gets persisted) once a later block `safety_margin` deep
+    // has been folded on top of it.
+    pending_for_store:
+        std::sync::Mutex<std::collections::VecDeque<(Block, DescartesV2State)>>,
 }
 
 impl<DA: DelegateAccess + Send + Sync + 'static> DescartesV2FoldDelegate<DA> {
     pub fn new(
         descartesv2_address: Address,
         epoch_fold: Arc<StateFold<EpochFoldDelegate<DA>, DA>>,
+        epoch_cache_capacity: Option<usize>,
+        checkpoint: Option<DescartesV2Checkpoint>,
+        state_store: Option<Arc<dyn StateStore>>,
+        safety_margin: usize,
     ) -> Self {
         Self {
             descartesv2_address,
             epoch_fold,
+            epoch_cache: epoch_cache_capacity.map(EpochFoldCache::new),
+            checkpoint,
+            transitions_log: std::sync::Mutex::new(Vec::new()),
+            state_store,
+            safety_margin: safety_margin as u64,
+            pending_for_store: std::sync::Mutex::new(
+                std::collections::VecDeque::new(),
+            ),
+        }
+    }
+
+    /// Pops every pending `(block, state)` pair that now has at least
+    /// `safety_margin` confirmations (i.e. is at least `safety_margin`
+    /// blocks behind `latest_block_number`), ready to be persisted.
+    fn drain_confirmed_for_store(
+        &self,
+        latest_block_number: u64,
+    ) -> Vec<(Block, DescartesV2State)> {
+        let confirmed_boundary =
+            latest_block_number.saturating_sub(self.safety_margin);
+
+        let mut pending = self.pending_for_store.lock().unwrap();
+        let mut confirmed = Vec::new();
+        while let Some((block, _)) = pending.front() {
+            if block.number.as_u64() > confirmed_boundary {
+                break;
+            }
+            confirmed.push(pending.pop_front().unwrap());
         }
+        confirmed
+    }
+
+    /// Returns every recorded epoch transition with `epoch_number` strictly
+    /// greater than `last_processed_epoch` (or all of them, if `None`).
+    /// A downstream actor is expected to track its own watermark (e.g. the
+    /// last epoch it reacted to) and pass it back in on every call so that,
+    /// after a restart, it doesn't re-fire reactions for transitions it
+    /// already processed.
+    ///
+    /// Also prunes the in-memory log down to `last_processed_epoch`, since
+    /// a caller reporting a watermark is telling us it no longer needs
+    /// anything at or before it. This keeps the log from growing without
+    /// bound over a long-running validator.
+    pub fn epoch_transitions_since(
+        &self,
+        last_processed_epoch: Option<U256>,
+    ) -> Vec<EpochTransition> {
+        let mut log = self.transitions_log.lock().unwrap();
+        if let Some(epoch) = last_processed_epoch {
+            log.retain(|t| t.epoch_number > epoch);
+        }
+        log.clone()
+    }
+
+    /// Resolves the `EpochState` accumulator for `block`, serving it from
+    /// the memoization cache when present instead of falling through to
+    /// `EpochFoldDelegate`.
+    async fn get_epoch_state(
+        &self,
+        initial_epoch: &U256,
+        block_hash: ethers::types::H256,
+    ) -> Result<EpochState, String> {
+        if let Some(cache) = &self.epoch_cache {
+            if let Some(state) = cache.get(initial_epoch, block_hash) {
+                return Ok(state);
+            }
+        }
+
+        let state = self
+            .epoch_fold
+            .get_state_for_block(initial_epoch, block_hash)
+            .await
+            .map_err(|e| format!("{:?}", e))?
+            .state;
+
+        if let Some(cache) = &self.epoch_cache {
+            cache.insert(*initial_epoch, block_hash, state.clone());
+        }
+
+        Ok(state)
+    }
+
+    /// The epochs the checkpoint already knew were finalized, if any. This
+    /// is the prefix of `finalized_epochs` that pre-dates the checkpoint
+    /// and therefore can't be recovered by folding forward from it.
+    fn checkpoint_finalized_epochs(&self) -> Option<Vec<FinalizedEpoch>> {
+        self.checkpoint
+            .as_ref()
+            .map(|checkpoint| checkpoint.state.finalized_epochs.clone())
     }
 }
 
@@ -55,6 +188,21 @@ impl<DA: DelegateAccess + Send + Sync + 'static> StateFoldDelegate
         block: &Block,
         access: &A,
     ) -> SyncResult<Self::Accumulator, A> {
+        // If we have a checkpoint, we can only serve blocks at or after it.
+        // Anything older is a genuine "can't answer this" rather than a
+        // fold failure, so it gets its own typed error.
+        if let Some(checkpoint) = &self.checkpoint {
+            if block.number < checkpoint.block.number {
+                return SyncDelegateError {
+                    err: DescartesV2DelegateError::StateUnavailable {
+                        requested_block: block.number.as_u64(),
+                        checkpoint_block: checkpoint.block.number.as_u64(),
+                    },
+                }
+                .fail();
+            }
+        }
+
         let middleware = access
             .build_sync_contract(Address::zero(), block.number, |_, m| m)
             .await;
@@ -64,8 +212,12 @@ impl<DA: DelegateAccess + Send + Sync + 'static> StateFoldDelegate
             Arc::clone(&middleware),
         );
 
-        // Retrieve constants from contract creation event
-        let constants = {
+        // Retrieve constants from contract creation event, unless a
+        // checkpoint already has them (constants never change after
+        // contract creation, so the checkpoint's copy is still valid).
+        let constants = if let Some(checkpoint) = &self.checkpoint {
+            checkpoint.state.constants.clone()
+        } else {
             let (create_event, meta) = {
                 let e = contract
                     .descartes_v2_created_filter()
@@ -100,54 +252,147 @@ impl<DA: DelegateAccess + Send + Sync + 'static> StateFoldDelegate
             ImmutableState::from(&(create_event, timestamp))
         };
 
-        // get raw state from EpochFoldDelegate
+        // Fold forward from the checkpoint's current epoch instead of the
+        // initial epoch whenever a checkpoint is available, so we don't
+        // replay epochs already folded into it.
+        let starting_epoch = self
+            .checkpoint
+            .as_ref()
+            .map(|checkpoint| &checkpoint.state.current_epoch.epoch_number)
+            .unwrap_or(initial_state);
+
+        // get raw state from EpochFoldDelegate, serving from the
+        // memoization cache when possible
         let raw_contract_state = self
-            .epoch_fold
-            .get_state_for_block(initial_state, block.hash)
+            .get_epoch_state(starting_epoch, block.hash)
             .await
             .map_err(|e| {
                 SyncDelegateError {
                     err: format!("Epoch state fold error: {:?}", e),
                 }
                 .build()
-            })?
-            .state;
+            })?;
 
-        Ok(convert_raw_to_logical(
+        let state = convert_raw_to_logical(
             raw_contract_state,
             constants,
             block,
-            initial_state,
-        ))
+            starting_epoch,
+            self.checkpoint_finalized_epochs().unwrap_or_default(),
+        );
+
+        // `sync` is the framework's generic path whenever a block must be
+        // resolved without a contiguous `fold` chain from a cached ancestor
+        // (a coverage gap after being offline, or a cache miss chain), so
+        // it can just as well jump across epoch transitions as `fold` can.
+        // Diff against the checkpoint (the only prior accumulator we have
+        // here) so those transitions aren't silently dropped.
+        let transitions = compute_epoch_transitions(
+            self.checkpoint.as_ref().map(|checkpoint| &checkpoint.state),
+            &state,
+            block.timestamp,
+        );
+        if !transitions.is_empty() {
+            self.transitions_log.lock().unwrap().extend(transitions);
+        }
+
+        Ok(state)
     }
 
     async fn fold<A: FoldAccess + Send + Sync>(
         &self,
         previous_state: &Self::Accumulator,
         block: &Block,
-        _access: &A,
+        access: &A,
     ) -> FoldResult<Self::Accumulator, A> {
         let constants = previous_state.constants.clone();
 
-        // get raw state from EpochFoldDelegate
+        // get raw state from EpochFoldDelegate, serving from the
+        // memoization cache when possible
         let raw_contract_state = self
-            .epoch_fold
-            .get_state_for_block(&previous_state.initial_epoch, block.hash)
+            .get_epoch_state(&previous_state.initial_epoch, block.hash)
             .await
             .map_err(|e| {
                 FoldDelegateError {
                     err: format!("Epoch state fold error: {:?}", e),
                 }
                 .build()
-            })?
-            .state;
+            })?;
 
-        Ok(convert_raw_to_logical(
+        let state = convert_raw_to_logical(
             raw_contract_state,
             constants,
             block,
             &previous_state.initial_epoch,
-        ))
+            previous_state.finalized_epochs.clone(),
+        );
+
+        let transitions = compute_epoch_transitions(
+            Some(previous_state),
+            &state,
+            block.timestamp,
+        );
+        if !transitions.is_empty() {
+            self.transitions_log.lock().unwrap().extend(transitions);
+        }
+
+        // `fold` can be, and per chunk0-1's own reorg-safety rationale is,
+        // called for blocks that aren't confirmed yet. Only blocks that
+        // are `safety_margin` deep relative to the latest one we've folded
+        // are safe to persist, so queue this one and flush whatever has
+        // since become old enough.
+        if let Some(state_store) = &self.state_store {
+            let confirmed = {
+                let mut pending = self.pending_for_store.lock().unwrap();
+                pending.push_back((block.clone(), state.clone()));
+                drop(pending);
+                self.drain_confirmed_for_store(block.number.as_u64())
+            };
+
+            if !confirmed.is_empty() {
+                // A queued entry may belong to a fork that was since
+                // reorg'd away (`fold` can run on blocks shallower than
+                // `safety_margin`). Check each one is still the canonical
+                // block at its height before trusting it as a checkpoint.
+                let middleware = access
+                    .build_fold_contract(Address::zero(), block.number, |_, m| m)
+                    .await;
+
+                for (confirmed_block, confirmed_state) in confirmed {
+                    match middleware.get_block(confirmed_block.number).await {
+                        Ok(Some(canonical))
+                            if canonical.hash == Some(confirmed_block.hash) =>
+                        {
+                            if let Err(e) = state_store
+                                .put(confirmed_block, confirmed_state)
+                                .await
+                            {
+                                log::warn!(
+                                    "Failed to persist fold state: {:?}",
+                                    e
+                                );
+                            }
+                        }
+                        Ok(_) => {
+                            log::warn!(
+                                "Skipping persistence of block {} ({:?}): no longer canonical",
+                                confirmed_block.number,
+                                confirmed_block.hash,
+                            );
+                        }
+                        Err(e) => {
+                            log::warn!(
+                                "Failed to verify block {} is canonical before persisting: {:?}",
+                                confirmed_block.number,
+                                e
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(state)
     }
 
     fn convert(
@@ -166,6 +411,7 @@ fn convert_raw_to_logical(
     constants: ImmutableState,
     block: &Block,
     initial_epoch: &U256,
+    known_finalized_epochs: Vec<FinalizedEpoch>,
 ) -> DescartesV2State {
     // If the raw state is InputAccumulation but it has expired, then the raw
     // state's `current_epoch` becomes the sealed epoch, and the logic state's
@@ -257,10 +503,32 @@ fn convert_raw_to_logical(
             }
         }
 
-        // This version doesn't have disputes. They're resolved automatically
-        // onchain
-        ContractPhase::AwaitingDispute { .. } => {
-            unreachable!()
+        ContractPhase::AwaitingDispute {
+            claimed_epoch,
+            dispute_start_ts,
+            round_start,
+        } => {
+            // Mirrors the AwaitingConsensus case: the dispute's own
+            // challenge period starts counting from the dispute start, but
+            // resets to the latest round if the dispute itself is
+            // challenged again before it times out.
+            let time_of_last_move =
+                std::cmp::max(dispute_start_ts, round_start);
+
+            if block.timestamp
+                > time_of_last_move + constants.challenge_period
+            {
+                PhaseState::DisputeTimeout {
+                    claimed_epoch,
+                    challenge_period_base_ts: time_of_last_move,
+                }
+            } else {
+                PhaseState::AwaitingDispute {
+                    claimed_epoch,
+                    dispute_start_ts,
+                    challenge_period_base_ts: time_of_last_move,
+                }
+            }
         }
     };
 
@@ -277,11 +545,37 @@ fn convert_raw_to_logical(
         constants,
         initial_epoch: *initial_epoch,
         current_phase: phase_state,
-        finalized_epochs: contract_state.finalized_epochs,
+        finalized_epochs: append_newly_finalized_epochs(
+            known_finalized_epochs,
+            contract_state.finalized_epochs,
+        ),
         current_epoch,
     }
 }
 
+// `contract_state.finalized_epochs` (from `EpochFoldDelegate`) is the
+// complete, ascending list of epochs finalized since this fold's own
+// starting epoch - a prefix of it is always already reflected in
+// `known_finalized_epochs` (the checkpoint's own history in `sync`, or the
+// previous fold's already-merged history in `fold`). Rather than
+// re-deriving the whole merged list from scratch on every call (an
+// unbounded cost for a long-running, post-checkpoint validator), find where
+// the already-known prefix ends and only append what's new past it.
+fn append_newly_finalized_epochs(
+    mut known_finalized_epochs: Vec<FinalizedEpoch>,
+    fresh_finalized_epochs: Vec<FinalizedEpoch>,
+) -> Vec<FinalizedEpoch> {
+    let already_known_up_to = match known_finalized_epochs.last() {
+        Some(last) => fresh_finalized_epochs
+            .partition_point(|epoch| epoch.epoch_number <= last.epoch_number),
+        None => 0,
+    };
+
+    known_finalized_epochs
+        .extend(fresh_finalized_epochs.into_iter().skip(already_known_up_to));
+    known_finalized_epochs
+}
+
 // Fetches the DescartesV2 constants from the contract creation event
 impl From<&(DescartesV2CreatedFilter, U256)> for ImmutableState {
     fn from(src: &(DescartesV2CreatedFilter, U256)) -> Self {
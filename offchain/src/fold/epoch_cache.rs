@@ -0,0 +1,127 @@
+use super::epoch_delegate::EpochState;
+
+use ethers::types::H256;
+use lru::LruCache;
+
+use std::sync::Mutex;
+
+use offchain_core::ethers;
+use ethers::types::U256;
+
+/// Bounded memoization cache for `EpochFoldDelegate` lookups, keyed by the
+/// `(initial_epoch, block_hash)` pair passed to `get_state_for_block`.
+///
+/// Keying on block hash, rather than block number, means a reorg simply
+/// misses the cache and is recomputed like any other block we haven't seen,
+/// instead of serving a stale accumulator for the wrong chain.
+pub struct EpochFoldCache {
+    cache: Mutex<LruCache<(U256, H256), EpochState>>,
+}
+
+impl EpochFoldCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    pub fn get(
+        &self,
+        initial_epoch: &U256,
+        block_hash: H256,
+    ) -> Option<EpochState> {
+        self.cache
+            .lock()
+            .unwrap()
+            .get(&(*initial_epoch, block_hash))
+            .cloned()
+    }
+
+    pub fn insert(
+        &self,
+        initial_epoch: U256,
+        block_hash: H256,
+        state: EpochState,
+    ) {
+        self.cache
+            .lock()
+            .unwrap()
+            .put((initial_epoch, block_hash), state);
+    }
+}
+
+/// A capacity callers can pass as `SetupConfig::epoch_cache_size`. It is not
+/// applied automatically: leaving `epoch_cache_size` as `None` disables the
+/// cache.
+pub const DEFAULT_EPOCH_CACHE_CAPACITY: usize = 500;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::epoch_delegate::ContractPhase;
+    use super::super::types::AccumulatingEpoch;
+
+    fn sample_epoch_state(epoch_number: u64) -> EpochState {
+        EpochState {
+            current_phase: ContractPhase::InputAccumulation {},
+            phase_change_timestamp: None,
+            current_epoch: AccumulatingEpoch::new(U256::from(epoch_number)),
+            finalized_epochs: vec![],
+        }
+    }
+
+    #[test]
+    fn misses_on_an_unknown_key() {
+        let cache = EpochFoldCache::new(10);
+        assert!(cache.get(&U256::zero(), H256::zero()).is_none());
+    }
+
+    #[test]
+    fn hits_after_an_insert() {
+        let cache = EpochFoldCache::new(10);
+        let hash = H256::from_low_u64_be(1);
+
+        cache.insert(U256::zero(), hash, sample_epoch_state(5));
+
+        let hit = cache.get(&U256::zero(), hash);
+        assert!(hit.is_some());
+        assert_eq!(hit.unwrap().current_epoch.epoch_number, U256::from(5));
+    }
+
+    #[test]
+    fn distinguishes_entries_by_both_initial_epoch_and_block_hash() {
+        let cache = EpochFoldCache::new(10);
+        let hash = H256::from_low_u64_be(1);
+
+        cache.insert(U256::zero(), hash, sample_epoch_state(5));
+
+        // Same hash, different initial epoch: must not collide.
+        assert!(cache.get(&U256::from(1), hash).is_none());
+        // Same initial epoch, different hash (e.g. a reorg): must miss
+        // instead of serving the state computed for the old fork.
+        assert!(cache
+            .get(&U256::zero(), H256::from_low_u64_be(2))
+            .is_none());
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_entry_past_capacity() {
+        let cache = EpochFoldCache::new(2);
+
+        let hash_a = H256::from_low_u64_be(1);
+        let hash_b = H256::from_low_u64_be(2);
+        let hash_c = H256::from_low_u64_be(3);
+
+        cache.insert(U256::zero(), hash_a, sample_epoch_state(1));
+        cache.insert(U256::zero(), hash_b, sample_epoch_state(2));
+
+        // Touch `hash_a` so `hash_b` becomes the least recently used.
+        assert!(cache.get(&U256::zero(), hash_a).is_some());
+
+        cache.insert(U256::zero(), hash_c, sample_epoch_state(3));
+
+        assert!(cache.get(&U256::zero(), hash_a).is_some());
+        assert!(cache.get(&U256::zero(), hash_b).is_none());
+        assert!(cache.get(&U256::zero(), hash_c).is_some());
+    }
+}
@@ -0,0 +1,324 @@
+use super::types::{DescartesV2State, FinalizedEpoch, PhaseState};
+
+use offchain_core::ethers;
+use ethers::types::U256;
+
+/// Coarse label for a `PhaseState`, used only to detect that *some*
+/// transition happened without caring about the data carried by each
+/// variant.
+fn phase_kind(phase: &PhaseState) -> &'static str {
+    match phase {
+        PhaseState::InputAccumulation {} => "InputAccumulation",
+        PhaseState::EpochSealedAwaitingFirstClaim { .. } => {
+            "EpochSealedAwaitingFirstClaim"
+        }
+        PhaseState::AwaitingConsensusNoConflict { .. } => {
+            "AwaitingConsensusNoConflict"
+        }
+        PhaseState::AwaitingConsensusAfterConflict { .. } => {
+            "AwaitingConsensusAfterConflict"
+        }
+        PhaseState::ConsensusTimeout { .. } => "ConsensusTimeout",
+        PhaseState::AwaitingDispute { .. } => "AwaitingDispute",
+        PhaseState::DisputeTimeout { .. } => "DisputeTimeout",
+    }
+}
+
+/// A `PhaseState`, or the terminal "left `current_phase` because it
+/// finalized" state that `DescartesV2State` doesn't otherwise represent
+/// (a finalized epoch simply drops off `current_phase` and into
+/// `finalized_epochs`).
+#[derive(Clone, Debug)]
+pub enum EpochPhase {
+    Phase(PhaseState),
+    Finalized,
+}
+
+/// A logical phase change for a single epoch, detected by diffing the
+/// previous fold accumulator against the new one. A downstream actor can
+/// watch a stream of these to drive exactly-once reactions (submit claim,
+/// finalize, start/respond to dispute) instead of re-deriving the diff
+/// itself.
+#[derive(Clone, Debug)]
+pub struct EpochTransition {
+    pub epoch_number: U256,
+    pub from_phase: EpochPhase,
+    pub to_phase: EpochPhase,
+    pub at_timestamp: U256,
+}
+
+/// Compares `previous` (the accumulator before this fold) against
+/// `current` and returns the epoch transitions that happened in between,
+/// if any. Returns nothing on the very first fold (`previous == None`),
+/// since there is nothing yet to diff against.
+///
+/// Finalization is detected from `finalized_epochs` itself, not from
+/// `current_epoch`'s number: the latter can advance for reasons other than
+/// a finalization. In particular, `convert_raw_to_logical` synthesizes the
+/// next epoch's number when input accumulation simply times out with no
+/// new input, even though the old epoch has only just sealed (awaiting its
+/// first claim), not finalized.
+///
+/// A single fold step can still finalize more than one epoch at once (e.g.
+/// after a gap in syncing), and can simultaneously move the brand-new
+/// "current" epoch through its own first phase change (e.g. it seals
+/// immediately because its input duration already elapsed). Both are
+/// reported independently of one another.
+pub fn compute_epoch_transitions(
+    previous: Option<&DescartesV2State>,
+    current: &DescartesV2State,
+    at_timestamp: U256,
+) -> Vec<EpochTransition> {
+    let previous = match previous {
+        Some(p) => p,
+        None => return Vec::new(),
+    };
+
+    let mut transitions = Vec::new();
+
+    let previously_finalized: std::collections::HashSet<U256> = previous
+        .finalized_epochs
+        .iter()
+        .map(|epoch| epoch.epoch_number)
+        .collect();
+
+    for epoch in &current.finalized_epochs {
+        if !previously_finalized.contains(&epoch.epoch_number) {
+            transitions.push(EpochTransition {
+                epoch_number: epoch.epoch_number,
+                from_phase: EpochPhase::Phase(previous.current_phase.clone()),
+                to_phase: EpochPhase::Finalized,
+                at_timestamp,
+            });
+        }
+    }
+
+    let previous_epoch_number = previous.current_epoch.epoch_number;
+    let current_epoch_number = current.current_epoch.epoch_number;
+
+    if current_epoch_number == previous_epoch_number {
+        // Same epoch: its phase may have moved on its own (sealed, claimed,
+        // disputed, timed out...).
+        if phase_kind(&previous.current_phase)
+            != phase_kind(&current.current_phase)
+        {
+            transitions.push(EpochTransition {
+                epoch_number: current_epoch_number,
+                from_phase: EpochPhase::Phase(previous.current_phase.clone()),
+                to_phase: EpochPhase::Phase(current.current_phase.clone()),
+                at_timestamp,
+            });
+        }
+    } else if phase_kind(&current.current_phase)
+        != phase_kind(&PhaseState::InputAccumulation {})
+    {
+        // The new current epoch always starts out in InputAccumulation;
+        // report its own transition independently of any finalization(s)
+        // above if it has already moved past that within this same fold.
+        transitions.push(EpochTransition {
+            epoch_number: current_epoch_number,
+            from_phase: EpochPhase::Phase(PhaseState::InputAccumulation {}),
+            to_phase: EpochPhase::Phase(current.current_phase.clone()),
+            at_timestamp,
+        });
+    }
+
+    transitions
+}
+
+/// A fixed, arbitrary `ImmutableState` for tests that don't care about the
+/// constants themselves, just that `DescartesV2State` has some. Shared
+/// across this module's and `state_store`'s test fixtures so the same
+/// literal isn't maintained in two places.
+#[cfg(test)]
+pub(crate) fn sample_immutable_state() -> super::types::ImmutableState {
+    use offchain_core::ethers::types::Address;
+
+    super::types::ImmutableState {
+        input_duration: U256::from(100),
+        challenge_period: U256::from(100),
+        contract_creation_timestamp: U256::zero(),
+        input_contract_address: Address::zero(),
+        output_contract_address: Address::zero(),
+        validator_contract_address: Address::zero(),
+        dispute_contract_address: Address::zero(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::AccumulatingEpoch;
+
+    fn state(
+        epoch_number: u64,
+        phase: PhaseState,
+        finalized_epochs: Vec<FinalizedEpoch>,
+    ) -> DescartesV2State {
+        DescartesV2State {
+            constants: sample_immutable_state(),
+            initial_epoch: U256::zero(),
+            current_phase: phase,
+            finalized_epochs,
+            current_epoch: AccumulatingEpoch::new(U256::from(epoch_number)),
+        }
+    }
+
+    fn finalized(epoch_number: u64) -> FinalizedEpoch {
+        FinalizedEpoch::new(U256::from(epoch_number))
+    }
+
+    #[test]
+    fn no_transition_when_nothing_changed() {
+        let previous = state(3, PhaseState::InputAccumulation {}, vec![]);
+        let current = state(3, PhaseState::InputAccumulation {}, vec![]);
+
+        assert!(compute_epoch_transitions(
+            Some(&previous),
+            &current,
+            U256::from(1)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn reports_phase_change_within_the_same_epoch() {
+        let previous = state(3, PhaseState::InputAccumulation {}, vec![]);
+        let current = state(
+            3,
+            PhaseState::EpochSealedAwaitingFirstClaim {
+                sealed_epoch: AccumulatingEpoch::new(U256::from(3)),
+            },
+            vec![],
+        );
+
+        let transitions = compute_epoch_transitions(
+            Some(&previous),
+            &current,
+            U256::from(1),
+        );
+
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].epoch_number, U256::from(3));
+    }
+
+    #[test]
+    fn does_not_report_a_finalization_when_accumulation_merely_seals() {
+        // InputAccumulation(3) timing out with no new input bumps
+        // `current_epoch` from 3 to 4 without finalizing epoch 3 - it only
+        // seals, awaiting its first claim.
+        let previous = state(3, PhaseState::InputAccumulation {}, vec![]);
+        let current = state(
+            4,
+            PhaseState::EpochSealedAwaitingFirstClaim {
+                sealed_epoch: AccumulatingEpoch::new(U256::from(3)),
+            },
+            vec![],
+        );
+
+        let transitions = compute_epoch_transitions(
+            Some(&previous),
+            &current,
+            U256::from(1),
+        );
+
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].epoch_number, U256::from(4));
+        assert!(!matches!(transitions[0].to_phase, EpochPhase::Finalized));
+    }
+
+    #[test]
+    fn reports_every_epoch_finalized_in_a_multi_epoch_gap() {
+        let previous = state(
+            3,
+            PhaseState::EpochSealedAwaitingFirstClaim {
+                sealed_epoch: AccumulatingEpoch::new(U256::from(3)),
+            },
+            vec![],
+        );
+        let current = state(
+            6,
+            PhaseState::InputAccumulation {},
+            vec![finalized(3), finalized(4), finalized(5)],
+        );
+
+        let transitions = compute_epoch_transitions(
+            Some(&previous),
+            &current,
+            U256::from(1),
+        );
+
+        // Epochs 3, 4 and 5 all finalized within this single fold step.
+        assert_eq!(transitions.len(), 3);
+        let finalized_epochs: Vec<U256> = transitions
+            .iter()
+            .filter(|t| matches!(t.to_phase, EpochPhase::Finalized))
+            .map(|t| t.epoch_number)
+            .collect();
+        assert_eq!(
+            finalized_epochs,
+            vec![U256::from(3), U256::from(4), U256::from(5)]
+        );
+    }
+
+    #[test]
+    fn reports_new_epochs_own_transition_alongside_finalizations() {
+        let previous = state(
+            3,
+            PhaseState::EpochSealedAwaitingFirstClaim {
+                sealed_epoch: AccumulatingEpoch::new(U256::from(3)),
+            },
+            vec![],
+        );
+        let current = state(
+            4,
+            PhaseState::EpochSealedAwaitingFirstClaim {
+                sealed_epoch: AccumulatingEpoch::new(U256::from(4)),
+            },
+            vec![finalized(3)],
+        );
+
+        let transitions = compute_epoch_transitions(
+            Some(&previous),
+            &current,
+            U256::from(1),
+        );
+
+        // Epoch 3 finalizes *and* the brand-new epoch 4 immediately seals,
+        // both within the same fold step.
+        assert_eq!(transitions.len(), 2);
+        assert_eq!(transitions[0].epoch_number, U256::from(3));
+        assert!(matches!(transitions[0].to_phase, EpochPhase::Finalized));
+        assert_eq!(transitions[1].epoch_number, U256::from(4));
+    }
+
+    #[test]
+    fn does_not_re_report_an_already_known_finalization() {
+        let previous = state(
+            6,
+            PhaseState::InputAccumulation {},
+            vec![finalized(3), finalized(4), finalized(5)],
+        );
+        let current = state(
+            6,
+            PhaseState::InputAccumulation {},
+            vec![finalized(3), finalized(4), finalized(5)],
+        );
+
+        assert!(compute_epoch_transitions(
+            Some(&previous),
+            &current,
+            U256::from(1)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn reports_nothing_on_the_first_fold() {
+        let current = state(0, PhaseState::InputAccumulation {}, vec![]);
+        assert!(
+            compute_epoch_transitions(None, &current, U256::from(1))
+                .is_empty()
+        );
+    }
+}
@@ -11,28 +11,59 @@ pub struct SetupConfig {
     pub safety_margin: usize,
     pub input_contract_address: Address,
     pub descartes_contract_address: Address,
+
+    /// Capacity of the `(initial_epoch, block_hash)` memoization cache kept
+    /// in front of `EpochFoldDelegate`. `None` disables the cache.
+    pub epoch_cache_size: Option<usize>,
+
+    /// Where to persist finalized `DescartesV2State` accumulators for
+    /// crash recovery. `None` disables persistence entirely.
+    pub state_store: Option<Arc<dyn StateStore>>,
 }
 
 pub type DescartesStateFold<DA> =
     Arc<StateFold<DescartesV2FoldDelegate<DA>, DA>>;
 
-/// Creates DescartesV2 State Fold
-pub fn create_descartes_state_fold<
+/// Creates DescartesV2 State Fold.
+///
+/// If `checkpoint` isn't given explicitly and `config.state_store` is set,
+/// the most recently persisted finalized accumulator is loaded and used as
+/// the checkpoint to fold forward from, so a restart doesn't replay from
+/// contract creation.
+pub async fn create_descartes_state_fold<
     DA: DelegateAccess + Send + Sync + 'static,
 >(
     access: Arc<DA>,
     config: &SetupConfig,
+    checkpoint: Option<DescartesV2Checkpoint>,
 ) -> DescartesStateFold<DA> {
     let epoch_fold = create_epoch(Arc::clone(&access), config);
 
+    let checkpoint = match checkpoint {
+        Some(checkpoint) => Some(checkpoint),
+        None => load_checkpoint(config.state_store.as_deref()).await,
+    };
+
     let delegate = DescartesV2FoldDelegate::new(
         config.descartes_contract_address,
         epoch_fold,
+        config.epoch_cache_size,
+        checkpoint,
+        config.state_store.clone(),
+        config.safety_margin,
     );
     let state_fold = StateFold::new(delegate, access, config.safety_margin);
     Arc::new(state_fold)
 }
 
+async fn load_checkpoint(
+    state_store: Option<&dyn StateStore>,
+) -> Option<DescartesV2Checkpoint> {
+    let (block, state) =
+        state_store?.latest_finalized().await.ok().flatten()?;
+    Some(DescartesV2Checkpoint { block, state })
+}
+
 type InputStateFold<DA> = Arc<StateFold<InputFoldDelegate, DA>>;
 fn create_input<DA: DelegateAccess + Send + Sync + 'static>(
     access: Arc<DA>,
@@ -0,0 +1,268 @@
+use super::types::DescartesV2State;
+
+use offchain_core::ethers;
+use offchain_core::types::Block;
+
+use async_trait::async_trait;
+use ethers::types::H256;
+use serde::{de::DeserializeOwned, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Snafu)]
+pub enum StateStoreError {
+    #[snafu(display("I/O error persisting state: {}", source))]
+    Io { source: std::io::Error },
+
+    #[snafu(display("Error (de)serializing persisted state: {}", source))]
+    Serialization { source: serde_json::Error },
+}
+
+/// Persists computed `DescartesV2State` accumulators so a validator doesn't
+/// have to replay the whole chain on every restart. Implementations are
+/// only ever handed states the caller has already judged safe from reorgs
+/// (i.e. at least `safety_margin` blocks deep), so `latest_finalized` can
+/// be trusted as a checkpoint to fold forward from.
+#[async_trait]
+pub trait StateStore: Send + Sync {
+    async fn put(
+        &self,
+        block: Block,
+        state: DescartesV2State,
+    ) -> Result<(), StateStoreError>;
+
+    async fn get(
+        &self,
+        block_hash: H256,
+    ) -> Result<Option<DescartesV2State>, StateStoreError>;
+
+    async fn latest_finalized(
+        &self,
+    ) -> Result<Option<(Block, DescartesV2State)>, StateStoreError>;
+}
+
+/// In-memory `StateStore`. Keeps every persisted state for the lifetime of
+/// the process; nothing survives a restart. Useful as a default/no-op
+/// persistence layer and in tests.
+#[derive(Default)]
+pub struct InMemoryStateStore {
+    states: Mutex<HashMap<H256, (Block, DescartesV2State)>>,
+    latest: Mutex<Option<H256>>,
+}
+
+impl InMemoryStateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl StateStore for InMemoryStateStore {
+    async fn put(
+        &self,
+        block: Block,
+        state: DescartesV2State,
+    ) -> Result<(), StateStoreError> {
+        let hash = block.hash;
+        self.states.lock().unwrap().insert(hash, (block, state));
+        *self.latest.lock().unwrap() = Some(hash);
+        Ok(())
+    }
+
+    async fn get(
+        &self,
+        block_hash: H256,
+    ) -> Result<Option<DescartesV2State>, StateStoreError> {
+        Ok(self
+            .states
+            .lock()
+            .unwrap()
+            .get(&block_hash)
+            .map(|(_, state)| state.clone()))
+    }
+
+    async fn latest_finalized(
+        &self,
+    ) -> Result<Option<(Block, DescartesV2State)>, StateStoreError> {
+        let latest = *self.latest.lock().unwrap();
+        Ok(latest.and_then(|hash| self.states.lock().unwrap().get(&hash).cloned()))
+    }
+}
+
+/// File-backed `StateStore`. Each persisted state is written as one JSON
+/// file per block hash under `directory`, plus a small `latest` file
+/// recording which one is the most recently persisted finalized state.
+///
+/// This is the only on-disk `StateStore` in this module; there is no
+/// RocksDB-backed implementation. Nothing is compacted or pruned: every
+/// persisted state stays on disk as its own file.
+pub struct FileStateStore {
+    directory: PathBuf,
+}
+
+impl FileStateStore {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    fn state_path(&self, block_hash: H256) -> PathBuf {
+        self.directory.join(format!("{:x}.json", block_hash))
+    }
+
+    fn latest_path(&self) -> PathBuf {
+        self.directory.join("latest")
+    }
+}
+
+#[async_trait]
+impl StateStore for FileStateStore {
+    async fn put(
+        &self,
+        block: Block,
+        state: DescartesV2State,
+    ) -> Result<(), StateStoreError> {
+        std::fs::create_dir_all(&self.directory).context(Io {})?;
+
+        let entry = (block.clone(), state);
+        let serialized = serde_json::to_vec(&entry).context(Serialization {})?;
+        std::fs::write(self.state_path(block.hash), serialized)
+            .context(Io {})?;
+
+        std::fs::write(self.latest_path(), format!("{:x}", block.hash))
+            .context(Io {})?;
+
+        Ok(())
+    }
+
+    async fn get(
+        &self,
+        block_hash: H256,
+    ) -> Result<Option<DescartesV2State>, StateStoreError> {
+        read_entry(&self.state_path(block_hash))
+            .map(|entry| entry.map(|(_, state)| state))
+    }
+
+    async fn latest_finalized(
+        &self,
+    ) -> Result<Option<(Block, DescartesV2State)>, StateStoreError> {
+        let latest_hash = match std::fs::read_to_string(self.latest_path()) {
+            Ok(s) => s,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e).context(Io {}),
+        };
+
+        let block_hash: H256 =
+            latest_hash.trim().parse().map_err(|_| {
+                StateStoreError::Io {
+                    source: std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "malformed latest-state pointer",
+                    ),
+                }
+            })?;
+
+        read_entry(&self.state_path(block_hash))
+    }
+}
+
+fn read_entry<T: Serialize + DeserializeOwned>(
+    path: &Path,
+) -> Result<Option<T>, StateStoreError> {
+    match std::fs::read(path) {
+        Ok(bytes) => {
+            Ok(Some(serde_json::from_slice(&bytes).context(Serialization {})?))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).context(Io {}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::finality::sample_immutable_state;
+    use super::super::types::{AccumulatingEpoch, PhaseState};
+
+    use ethers::types::{U256, U64};
+
+    fn sample_block(number: u64) -> Block {
+        Block {
+            hash: H256::from_low_u64_be(number),
+            number: U64::from(number),
+            timestamp: U256::from(1_000 + number),
+        }
+    }
+
+    fn sample_state(epoch_number: u64) -> DescartesV2State {
+        DescartesV2State {
+            constants: sample_immutable_state(),
+            initial_epoch: U256::zero(),
+            current_phase: PhaseState::InputAccumulation {},
+            finalized_epochs: vec![],
+            current_epoch: AccumulatingEpoch::new(U256::from(epoch_number)),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir()
+            .join(format!("descartesv2-state-store-test-{}-{}", name, nanos))
+    }
+
+    #[tokio::test]
+    async fn round_trips_put_get_and_latest_finalized() {
+        let store = FileStateStore::new(temp_dir("round-trip"));
+
+        let block = sample_block(42);
+        let state = sample_state(7);
+
+        store.put(block.clone(), state.clone()).await.unwrap();
+
+        let fetched = store.get(block.hash).await.unwrap();
+        assert_eq!(
+            fetched.unwrap().current_epoch.epoch_number,
+            state.current_epoch.epoch_number
+        );
+
+        let (latest_block, latest_state) =
+            store.latest_finalized().await.unwrap().unwrap();
+        assert_eq!(latest_block.hash, block.hash);
+        assert_eq!(
+            latest_state.current_epoch.epoch_number,
+            state.current_epoch.epoch_number
+        );
+    }
+
+    #[tokio::test]
+    async fn get_returns_none_for_unknown_block() {
+        let store = FileStateStore::new(temp_dir("miss"));
+        assert!(store.get(H256::zero()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn latest_finalized_tracks_the_most_recent_put() {
+        let store = FileStateStore::new(temp_dir("latest"));
+
+        let first_block = sample_block(1);
+        let second_block = sample_block(2);
+
+        store.put(first_block, sample_state(1)).await.unwrap();
+        store
+            .put(second_block.clone(), sample_state(2))
+            .await
+            .unwrap();
+
+        let (latest_block, _) =
+            store.latest_finalized().await.unwrap().unwrap();
+        assert_eq!(latest_block.hash, second_block.hash);
+    }
+}